@@ -0,0 +1,201 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::marker::PhantomData;
+use std::os::raw::c_char;
+use so::Symbol;
+
+use Alpm;
+
+/// A handle to one of the registered sync databases (e.g. `core`, `extra`).
+///
+/// Obtained via [`Alpm::sync_dbs`]. Borrows the `Alpm` it came from so it
+/// can't outlive the `alpm_handle_t` its raw pointer actually points into.
+pub struct SyncDb<'a> {
+  handle: *const usize,
+  _alpm: PhantomData<&'a Alpm>,
+}
+
+/// A lightweight summary of a package, as returned by list-producing queries
+/// such as [`Alpm::search`].
+pub struct PackageInfo {
+  pub name: String,
+  pub version: String,
+}
+
+/// Walks an `alpm_list_t`, calling `f` on each node's `data` pointer and
+/// collecting the results. Shared by every libalpm call that returns a list.
+pub(crate) unsafe fn list_iter<T, F>(lib: &so::Library, list: *const usize, mut f: F) -> io::Result<Vec<T>>
+    where F: FnMut(*const usize) -> T {
+  // alpm_list_t *alpm_list_next(const alpm_list_t *list);
+  let list_next: Symbol<fn(*const usize) -> *const usize> = try!( lib.get(b"alpm_list_next\0") );
+
+  let mut result = Vec::new();
+  let mut node = list;
+  while node != std::ptr::null() {
+    // alpm_list_t stores its payload as the first field, so the node
+    // pointer itself can be reinterpreted as a pointer to the data.
+    let data = *(node as *const *const usize);
+    result.push(f(data));
+    node = list_next(node);
+  }
+
+  Ok(result)
+}
+
+/// Searches `db` for packages whose name or description matches every
+/// pattern in `patterns`, mirroring `alpm_db_search`.
+unsafe fn search_db(lib: &so::Library, db: *const usize, patterns: &[&str]) -> io::Result<Vec<PackageInfo>> {
+  // alpm_list_t *alpm_list_add(alpm_list_t *list, void *data);
+  let list_add: Symbol<fn(*const usize, *mut c_char) -> *const usize> = try!( lib.get(b"alpm_list_add\0") );
+  // alpm_list_t *alpm_db_search(alpm_db_t *db, const alpm_list_t *needles);
+  let db_search: Symbol<fn(*const usize, *const usize) -> *const usize> = try!( lib.get(b"alpm_db_search\0") );
+  // void alpm_list_free(alpm_list_t *list);
+  let list_free: Symbol<fn(*const usize)> = try!( lib.get(b"alpm_list_free\0") );
+  // const char *alpm_pkg_get_name(alpm_pkg_t *pkg);
+  let get_name: Symbol<fn(*const usize) -> *const c_char> = try!( lib.get(b"alpm_pkg_get_name\0") );
+  // const char *alpm_pkg_get_version(alpm_pkg_t *pkg);
+  let get_version: Symbol<fn(*const usize) -> *const c_char> = try!( lib.get(b"alpm_pkg_get_version\0") );
+
+  // Keep the needle CStrings alive for as long as the list references them.
+  let needles: Vec<CString> = try!(
+    patterns.iter().map(|p| CString::new(*p)).collect::<Result<_, _>>()
+  );
+
+  let mut needle_list: *const usize = std::ptr::null();
+  for needle in &needles {
+    needle_list = list_add(needle_list, needle.as_ptr() as *mut c_char);
+  }
+
+  let results = db_search(db, needle_list);
+
+  // Both needle_list and results are alpm_list_t chains owned by us (the
+  // packages behind `results`' nodes are cache-owned and untouched by
+  // alpm_list_free, which only frees the list shell) — free them now that
+  // list_iter has copied out the PackageInfos.
+  let packages = list_iter(lib, results, |pkg| PackageInfo {
+    name: CStr::from_ptr(get_name(pkg)).to_string_lossy().into_owned(),
+    version: CStr::from_ptr(get_version(pkg)).to_string_lossy().into_owned(),
+  });
+
+  list_free(results);
+  list_free(needle_list);
+
+  packages
+}
+
+impl Alpm {
+  /// List the sync databases registered in `pacman.conf`.
+  ///
+  /// This mirrors `alpm_get_syncdbs`.
+  pub fn sync_dbs<'a>(&'a self) -> io::Result<Vec<SyncDb<'a>>> {
+    unsafe {
+      // /** Get the list of sync databases.
+      //  * Returns a list of alpm_db_t structures, one for each registered
+      //  * sync database.
+      //  * @param handle the context handle
+      //  * @return a reference to an internal list of alpm_db_t structures
+      //  */
+      // alpm_list_t *alpm_get_syncdbs(alpm_handle_t *handle);
+      let get_syncdbs: Symbol<fn(*const usize) -> *const usize> = try!( self.lib.get(b"alpm_get_syncdbs\0") );
+
+      list_iter(&self.lib, get_syncdbs(self.handle), |data| SyncDb { handle: data, _alpm: PhantomData })
+    }
+  }
+
+  /// Reimplements `alpm_sync_newversion`: look up `pkg` in each registered sync
+  /// database in order and return its version if it is newer than the
+  /// installed one.
+  ///
+  /// Returns `Ok(None)` if `pkg` is not installed, not found in any sync
+  /// database, or already up to date. Stops at the first sync database that
+  /// carries the package, matching libalpm's first-occurrence rule.
+  pub fn find_upgrade(&self, pkg: &str) -> io::Result<Option<String>> {
+    let installed = match self.query_package_version(pkg) {
+      Ok(version) => version,
+      Err(_) => return Ok(None),
+    };
+
+    let name = try!( CString::new(pkg) );
+    let installed_cs = try!( CString::new(installed) );
+
+    unsafe {
+      // alpm_list_t *alpm_db_get_pkgcache(alpm_db_t *db);
+      let db_get_pkgcache: Symbol<fn(*const usize) -> *const usize> = try!( self.lib.get(b"alpm_db_get_pkgcache\0") );
+      // alpm_pkg_t *alpm_pkg_find(alpm_list_t *haystack, const char *needle);
+      let pkg_find_in_list: Symbol<fn(*const usize, *const c_char) -> *const usize> = try!( self.lib.get(b"alpm_pkg_find\0") );
+      // const char *alpm_pkg_get_version(alpm_pkg_t *pkg);
+      let get_version: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_version\0") );
+      // int alpm_pkg_vercmp(const char *a, const char *b)
+      let pkg_vercmp: Symbol<fn(*const c_char, *const c_char) -> *const i32> = try!( self.lib.get(b"alpm_pkg_vercmp\0") );
+
+      for db in try!( self.sync_dbs() ) {
+        let list = db_get_pkgcache(db.handle);
+        let found = pkg_find_in_list(list, name.as_ptr());
+
+        if found == std::ptr::null() {
+          continue;
+        }
+
+        let sync_version = get_version(found);
+        let cmp = pkg_vercmp(sync_version, installed_cs.as_ptr()) as i32;
+
+        return Ok(if cmp > 0 {
+          Some(CStr::from_ptr(sync_version).to_string_lossy().into_owned())
+        } else {
+          None
+        });
+      }
+
+      Ok(None)
+    }
+  }
+
+  /// Search the local database for packages matching every regex in
+  /// `patterns`, like `pacman -Qs`.
+  pub fn search(&self, patterns: &[&str]) -> io::Result<Vec<PackageInfo>> {
+    unsafe {
+      // alpm_db_t *alpm_get_localdb(alpm_handle_t *handle);
+      let get_localdb: Symbol<fn(*const usize) -> *const usize> = try!( self.lib.get(b"alpm_get_localdb\0") );
+
+      search_db(&self.lib, get_localdb(self.handle), patterns)
+    }
+  }
+}
+
+impl<'a> SyncDb<'a> {
+  /// Search this sync database for packages matching every regex in
+  /// `patterns`, like `pacman -Ss`.
+  pub fn search(&self, alpm: &Alpm, patterns: &[&str]) -> io::Result<Vec<PackageInfo>> {
+    unsafe { search_db(&alpm.lib, self.handle, patterns) }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use Alpm;
+
+  #[test]
+  fn sync_dbs_lists_at_least_one_db() {
+    let pacman = Alpm::new().unwrap();
+    assert!(!pacman.sync_dbs().unwrap().is_empty());
+  }
+
+  #[test]
+  fn find_upgrade_for_uptodate_pkg() {
+    let pacman = Alpm::new().unwrap();
+    assert_eq!(None, pacman.find_upgrade("pacman").unwrap());
+  }
+
+  #[test]
+  fn find_upgrade_for_missing_pkg() {
+    let pacman = Alpm::new().unwrap();
+    assert_eq!(None, pacman.find_upgrade("this-package-does-not-exist").unwrap());
+  }
+
+  #[test]
+  fn search_local_db_finds_installed_pkg() {
+    let pacman = Alpm::new().unwrap();
+    let results = pacman.search(&["pacman"]).unwrap();
+    assert!(results.iter().any(|p| p.name == "pacman"));
+  }
+}
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int};
+use so::Symbol;
+
+use Alpm;
+
+// statvfs(3) only needs the fields below; the rest of the kernel struct is
+// left unread but must still be there for the layout to line up.
+#[repr(C)]
+struct Statvfs {
+  f_bsize: u64,
+  f_frsize: u64,
+  f_blocks: u64,
+  f_bfree: u64,
+  f_bavail: u64,
+  f_files: u64,
+  f_ffree: u64,
+  f_favail: u64,
+  f_fsid: u64,
+  f_flag: u64,
+  f_namemax: u64,
+  // glibc's struct statvfs reserves this trailing padding; without it the
+  // real 112-byte struct overruns our 88-byte one on every call.
+  __f_spare: [i32; 6],
+}
+
+extern "C" {
+  fn statvfs(path: *const c_char, buf: *mut Statvfs) -> c_int;
+}
+
+/// Mirrors libalpm's `alpm_file_t`. We only read `name`/`size`, but `mode`
+/// still has to be declared (and `size` kept 64-bit) or this
+/// struct's size won't match the real one and indexing `files[i]` below
+/// will walk off into the wrong bytes.
+#[repr(C)]
+struct CFile {
+  name: *const c_char,
+  size: i64,
+  mode: u32,
+}
+
+/// Mirrors libalpm's `alpm_filelist_t`.
+#[repr(C)]
+struct CFileList {
+  count: usize,
+  files: *const CFile,
+}
+
+fn free_space(mountpoint: &str) -> io::Result<i64> {
+  let cpath = try!( CString::new(mountpoint) );
+  let mut stat: Statvfs = unsafe { std::mem::zeroed() };
+
+  if unsafe { statvfs(cpath.as_ptr(), &mut stat) } != 0 {
+    return Err(Error::new(ErrorKind::Other, format!("Could not determine free disk space on {}", mountpoint)));
+  }
+
+  Ok(stat.f_bavail as i64 * stat.f_frsize as i64)
+}
+
+/// Reads `/proc/self/mounts` for the mountpoint of every currently-mounted
+/// filesystem, so a file's target mountpoint can be resolved by longest
+/// matching prefix (mirroring how pacman's own diskspace check walks
+/// `getmntent`).
+fn list_mountpoints() -> io::Result<Vec<String>> {
+  let mounts = try!( std::fs::read_to_string("/proc/self/mounts") );
+
+  Ok(mounts.lines()
+    .filter_map(|line| line.split_whitespace().nth(1))
+    .map(|s| s.to_owned())
+    .collect())
+}
+
+/// Finds the mountpoint `file_path` (an absolute path under the install
+/// root) would actually land on, i.e. the longest mountpoint that is a
+/// prefix of it.
+fn resolve_mountpoint<'a>(mountpoints: &'a [String], file_path: &str) -> &'a str {
+  mountpoints.iter()
+    .filter(|m| file_path == m.as_str() || file_path.starts_with(m.as_str()) && (m.as_str() == "/" || file_path[m.len()..].starts_with('/')))
+    .max_by_key(|m| m.len())
+    .map(|m| m.as_str())
+    .unwrap_or("/")
+}
+
+impl Alpm {
+  /// Check whether there is enough free disk space to install the package
+  /// files at `package_paths`, mirroring libalpm's pre-commit diskspace
+  /// check (the source of `ALPM_ERR_DISK_SPACE`, which otherwise only
+  /// surfaces mid-`trans_commit`).
+  ///
+  /// Unlike a [`Package`](::Package) (which only ever describes an already
+  /// *installed* package, looked up in the local db — see
+  /// [`Alpm::get_package`]), these are the staged package files a caller is
+  /// about to pass to [`Transaction::add_pkg`](::Transaction::add_pkg), so
+  /// this can actually be called before the transaction that needs the
+  /// space. Each package's files are resolved to their target mountpoint
+  /// individually and checked against that mountpoint's free space, so
+  /// systems with `/home` or `/var` on a separate partition from `/` are
+  /// accounted for correctly.
+  pub fn check_diskspace(&self, package_paths: &[&str]) -> io::Result<bool> {
+    let mountpoints = try!( list_mountpoints() );
+    let mut required: HashMap<String, i64> = HashMap::new();
+
+    for path in package_paths {
+      let filename = try!( CString::new(*path) );
+
+      unsafe {
+        // int alpm_pkg_load(alpm_handle_t *handle, const char *filename, int full,
+        //                    alpm_siglevel_t level, alpm_pkg_t **pkg);
+        let pkg_load: Symbol<fn(*const usize, *const c_char, c_int, u32, *mut *const usize) -> c_int> =
+          try!( self.lib.get(b"alpm_pkg_load\0") );
+        // void alpm_pkg_free(alpm_pkg_t *pkg);
+        let pkg_free: Symbol<fn(*const usize)> = try!( self.lib.get(b"alpm_pkg_free\0") );
+        // alpm_filelist_t *alpm_pkg_get_files(alpm_pkg_t *pkg);
+        let get_files: Symbol<fn(*const usize) -> *const CFileList> = try!( self.lib.get(b"alpm_pkg_get_files\0") );
+
+        let mut pkg: *const usize = std::ptr::null();
+        if pkg_load(self.handle, filename.as_ptr(), 1, 0, &mut pkg) != 0 {
+          return Err(Error::new(ErrorKind::Other, format!("Could not load package {}", path)));
+        }
+
+        let filelist = &*get_files(pkg);
+        for i in 0..filelist.count {
+          let file = &*filelist.files.offset(i as isize);
+          let name = std::ffi::CStr::from_ptr(file.name).to_string_lossy();
+          let target = format!("/{}", name);
+          let mountpoint = resolve_mountpoint(&mountpoints, &target);
+
+          *required.entry(mountpoint.to_owned()).or_insert(0) += file.size;
+        }
+
+        pkg_free(pkg);
+      }
+    }
+
+    for (mountpoint, needed) in &required {
+      if *needed <= 0 {
+        continue;
+      }
+
+      if *needed > try!( free_space(mountpoint) ) {
+        return Ok(false);
+      }
+    }
+
+    Ok(true)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::resolve_mountpoint;
+
+  fn mounts() -> Vec<String> {
+    vec!["/".to_owned(), "/home".to_owned(), "/var".to_owned(), "/var/lib/pacman".to_owned()]
+  }
+
+  #[test]
+  fn picks_longest_matching_prefix() {
+    assert_eq!("/var/lib/pacman", resolve_mountpoint(&mounts(), "/var/lib/pacman/local/foo"));
+    assert_eq!("/var", resolve_mountpoint(&mounts(), "/var/log/pacman.log"));
+    assert_eq!("/home", resolve_mountpoint(&mounts(), "/home/alice/.cache"));
+  }
+
+  #[test]
+  fn falls_back_to_root() {
+    assert_eq!("/", resolve_mountpoint(&mounts(), "/usr/bin/pacman"));
+  }
+
+  #[test]
+  fn does_not_match_on_shared_prefix_without_separator() {
+    // "/varlib" is not under "/var" just because it shares a string prefix.
+    assert_eq!("/", resolve_mountpoint(&mounts(), "/varlib/foo"));
+  }
+}
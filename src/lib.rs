@@ -18,6 +18,19 @@ extern crate libloading as so;
 extern crate ini;
 extern crate num;
 
+mod db;
+mod diskspace;
+mod package;
+mod sig;
+mod trans;
+mod version;
+
+pub use db::{SyncDb, PackageInfo};
+pub use package::{Package, InstallReason};
+pub use sig::SigStatus;
+pub use trans::{Transaction, TransFlags};
+pub use version::{Version, Operator};
+
 use ini::Ini;
 use num::FromPrimitive;
 use std::cmp::Ordering;
@@ -0,0 +1,115 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_longlong};
+use num::FromPrimitive;
+use so::Symbol;
+
+use Alpm;
+
+enum_from_primitive! {
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+/// Why a package ended up installed, mirroring `alpm_pkgreason_t`.
+pub enum InstallReason {
+	Explicit = 0,
+	Depend = 1,
+}
+}
+
+/// Rich metadata for an installed package, as returned by [`Alpm::get_package`].
+///
+/// Unlike [`Alpm::query_package_version`], this keeps the fields libalpm
+/// exposes for `pacman -Qi`-style reporting instead of discarding everything
+/// but the version.
+pub struct Package {
+  pub name: String,
+  pub version: String,
+  pub desc: Option<String>,
+  pub url: Option<String>,
+  pub isize: i64,
+  pub builddate: i64,
+  pub packager: Option<String>,
+  pub reason: InstallReason,
+}
+
+impl Alpm {
+  /// Look up an installed package by name and return its full metadata.
+  pub fn get_package<S>(&self, name: S) -> io::Result<Package> where S: Into<String> {
+    let name: String = name.into();
+    let cs = try!( CString::new(name.clone()) );
+
+    unsafe {
+      // alpm_db_t *alpm_get_localdb(alpm_handle_t *handle);
+      let get_localdb: Symbol<fn(*const usize) -> *const usize> = try!( self.lib.get(b"alpm_get_localdb\0") );
+      // alpm_list_t *alpm_db_get_pkgcache(alpm_db_t *db);
+      let db_get_pkgcache: Symbol<fn(*const usize) -> *const usize> = try!( self.lib.get(b"alpm_db_get_pkgcache\0") );
+      // alpm_pkg_t *alpm_pkg_find(alpm_list_t *haystack, const char *needle);
+      let pkg_find_in_list: Symbol<fn(*const usize, *const c_char) -> *const usize> = try!( self.lib.get(b"alpm_pkg_find\0") );
+
+      let db = get_localdb(self.handle);
+      let list = db_get_pkgcache(db);
+      let pkg = pkg_find_in_list(list, cs.as_ptr());
+
+      if pkg == std::ptr::null() {
+        return Err(Error::new(ErrorKind::Other, format!("No package {} found!", name)));
+      }
+
+      // const char *alpm_pkg_get_name(alpm_pkg_t *pkg);
+      let get_name: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_name\0") );
+      // const char *alpm_pkg_get_version(alpm_pkg_t *pkg);
+      let get_version: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_version\0") );
+      // const char *alpm_pkg_get_desc(alpm_pkg_t *pkg);
+      let get_desc: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_desc\0") );
+      // const char *alpm_pkg_get_url(alpm_pkg_t *pkg);
+      let get_url: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_url\0") );
+      // off_t alpm_pkg_get_isize(alpm_pkg_t *pkg);
+      let get_isize: Symbol<fn(*const usize) -> c_longlong> = try!( self.lib.get(b"alpm_pkg_get_isize\0") );
+      // alpm_time_t alpm_pkg_get_builddate(alpm_pkg_t *pkg);
+      let get_builddate: Symbol<fn(*const usize) -> c_longlong> = try!( self.lib.get(b"alpm_pkg_get_builddate\0") );
+      // const char *alpm_pkg_get_packager(alpm_pkg_t *pkg);
+      let get_packager: Symbol<fn(*const usize) -> *const c_char> = try!( self.lib.get(b"alpm_pkg_get_packager\0") );
+      // alpm_pkgreason_t alpm_pkg_get_reason(alpm_pkg_t *pkg);
+      let get_reason: Symbol<fn(*const usize) -> u32> = try!( self.lib.get(b"alpm_pkg_get_reason\0") );
+
+      let to_string = |s: *const c_char| CStr::from_ptr(s).to_string_lossy().into_owned();
+      // alpm_pkg_get_desc/url/packager legitimately return NULL (no URL set,
+      // no recorded packager on a locally-built package, ...), unlike name
+      // and version which are always present on a found package.
+      let to_string_opt = |s: *const c_char| if s == std::ptr::null() {
+        None
+      } else {
+        Some(CStr::from_ptr(s).to_string_lossy().into_owned())
+      };
+
+      let reason = try!(
+        InstallReason::from_u32(get_reason(pkg))
+          .ok_or_else(|| Error::new(ErrorKind::Other, "Unknown install reason"))
+      );
+
+      Ok(Package {
+        name: to_string(get_name(pkg)),
+        version: to_string(get_version(pkg)),
+        desc: to_string_opt(get_desc(pkg)),
+        url: to_string_opt(get_url(pkg)),
+        isize: get_isize(pkg) as i64,
+        builddate: get_builddate(pkg) as i64,
+        packager: to_string_opt(get_packager(pkg)),
+        reason: reason,
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use Alpm;
+
+  #[test]
+  fn get_package_reads_metadata() {
+    let pacman = Alpm::new().unwrap();
+    let pkg = pacman.get_package("pacman").unwrap();
+    assert_eq!("pacman", pkg.name);
+    assert_eq!("5.0.2-2", pkg.version);
+  }
+}
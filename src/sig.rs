@@ -0,0 +1,213 @@
+use std::ffi::CString;
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int, c_uchar};
+use so::Symbol;
+
+use Alpm;
+
+/// Mirrors libalpm's `alpm_pgpkey_t`: the PGP key behind one signature result.
+#[repr(C)]
+struct CPgpKey {
+  data: *const usize,
+  fingerprint: *const c_char,
+  uid: *const c_char,
+  name: *const c_char,
+  email: *const c_char,
+  created: i64,
+  expires: i64,
+  length: u32,
+  revoked: u32,
+  pubkey_algo: c_char,
+}
+
+/// Mirrors libalpm's `alpm_sigresult_t`.
+#[repr(C)]
+struct CSigResult {
+  key: CPgpKey,
+  status: c_int,
+  validity: c_int,
+}
+
+/// Mirrors libalpm's `alpm_siglist_t`.
+#[repr(C)]
+struct CSigList {
+  count: usize,
+  results: *const CSigResult,
+}
+
+const ALPM_SIGSTATUS_VALID: c_int = 0;
+const ALPM_SIGSTATUS_KEY_EXPIRED: c_int = 1;
+const ALPM_SIGSTATUS_SIG_EXPIRED: c_int = 2;
+const ALPM_SIGSTATUS_KEY_UNKNOWN: c_int = 3;
+const ALPM_SIGSTATUS_KEY_DISABLED: c_int = 4;
+const ALPM_SIGSTATUS_INVALID: c_int = 5;
+
+// enough of a siglevel to ask for a detached package signature
+const ALPM_SIG_PACKAGE: u32 = 1 << 0;
+
+/// The outcome of checking a single PGP signature against a package file.
+#[derive(Debug, PartialEq)]
+pub enum SigStatus {
+  Valid,
+  Invalid,
+  KeyUnknown,
+  Missing,
+}
+
+/// Maps one `alpm_sigresult_t.status` value to a [`SigStatus`].
+fn status_from_code(status: c_int) -> SigStatus {
+  match status {
+    ALPM_SIGSTATUS_VALID => SigStatus::Valid,
+    ALPM_SIGSTATUS_KEY_UNKNOWN => SigStatus::KeyUnknown,
+    ALPM_SIGSTATUS_KEY_EXPIRED | ALPM_SIGSTATUS_SIG_EXPIRED |
+    ALPM_SIGSTATUS_KEY_DISABLED | ALPM_SIGSTATUS_INVALID => SigStatus::Invalid,
+    _ => SigStatus::Invalid,
+  }
+}
+
+impl Alpm {
+  /// Check the PGP signature(s) of the package file at `path`.
+  ///
+  /// libalpm only ever checks a signature it finds itself (a sibling
+  /// `path.sig` file, looked for when `full`/`level` are passed to
+  /// `alpm_pkg_load`) — there is no public entry point that takes an
+  /// explicit signature buffer at check time. So when `base64_sig` is
+  /// `Some`, it's decoded and written out to `path.sig` before loading the
+  /// package, standing in for the sibling file libalpm would otherwise look
+  /// for; the temporary file is removed again afterwards. When `base64_sig`
+  /// is `None`, whatever `path.sig` already exists (if any) is used as-is.
+  ///
+  /// The underlying libalpm call can complete normally (return `0`) while
+  /// still reporting an invalid or missing signature, so every signature's
+  /// status is returned rather than being collapsed into a single bool.
+  /// A failure of the check process itself (e.g. the package file can't be
+  /// read) surfaces as an `Err`, as does a `base64_sig` that can't be
+  /// decoded or written, or one supplied while `path.sig` already exists.
+  pub fn check_signature(&self, path: &str, base64_sig: Option<&str>) -> io::Result<Vec<SigStatus>> {
+    let sig_path = format!("{}.sig", path);
+
+    let wrote_sig = match base64_sig {
+      Some(encoded) => {
+        if std::path::Path::new(&sig_path).exists() {
+          return Err(Error::new(ErrorKind::Other,
+            format!("{} already exists; cannot supply an explicit signature", sig_path)));
+        }
+        let bytes = try!( unsafe { self.decode_signature(encoded) } );
+        try!( std::fs::write(&sig_path, &bytes) );
+        true
+      }
+      None => false,
+    };
+
+    let result = self.load_and_check_signature(path);
+
+    if wrote_sig {
+      let _ = std::fs::remove_file(&sig_path);
+    }
+
+    result
+  }
+
+  fn load_and_check_signature(&self, path: &str) -> io::Result<Vec<SigStatus>> {
+    let filename = try!( CString::new(path) );
+
+    unsafe {
+      // int alpm_pkg_load(alpm_handle_t *handle, const char *filename, int full,
+      //                    alpm_siglevel_t level, alpm_pkg_t **pkg);
+      let pkg_load: Symbol<fn(*const usize, *const c_char, c_int, u32, *mut *const usize) -> c_int> =
+        try!( self.lib.get(b"alpm_pkg_load\0") );
+      // void alpm_pkg_free(alpm_pkg_t *pkg);
+      let pkg_free: Symbol<fn(*const usize)> = try!( self.lib.get(b"alpm_pkg_free\0") );
+
+      let mut pkg: *const usize = std::ptr::null();
+      if pkg_load(self.handle, filename.as_ptr(), 1, ALPM_SIG_PACKAGE, &mut pkg) != 0 {
+        return Err(Error::new(ErrorKind::Other, format!("Could not load package {}", path)));
+      }
+
+      let result = self.check_pgp_signature(pkg, path);
+
+      pkg_free(pkg);
+
+      result
+    }
+  }
+
+  unsafe fn check_pgp_signature(&self, pkg: *const usize, path: &str) -> io::Result<Vec<SigStatus>> {
+    if !std::path::Path::new(&format!("{}.sig", path)).exists() {
+      return Ok(vec![SigStatus::Missing]);
+    }
+
+    // int alpm_pkg_check_pgp_signature(alpm_pkg_t *pkg, alpm_siglist_t *siglist);
+    let check: Symbol<fn(*const usize, *mut CSigList) -> c_int> =
+      try!( self.lib.get(b"alpm_pkg_check_pgp_signature\0") );
+
+    let mut siglist = CSigList { count: 0, results: std::ptr::null() };
+    if check(pkg, &mut siglist) != 0 && siglist.count == 0 {
+      return Err(Error::new(ErrorKind::Other, "Signature check process failed"));
+    }
+
+    let mut statuses = Vec::with_capacity(siglist.count);
+    for i in 0..siglist.count {
+      let result = &*siglist.results.offset(i as isize);
+      statuses.push(status_from_code(result.status));
+    }
+
+    // alpm_siglist_t owns heap memory allocated by libalpm; release it.
+    let cleanup: Symbol<fn(*mut CSigList) -> c_int> = try!( self.lib.get(b"alpm_siglist_cleanup\0") );
+    cleanup(&mut siglist);
+
+    if statuses.is_empty() {
+      statuses.push(SigStatus::Missing);
+    }
+
+    Ok(statuses)
+  }
+
+  unsafe fn decode_signature(&self, base64_sig: &str) -> io::Result<Vec<u8>> {
+    let encoded = try!( CString::new(base64_sig) );
+
+    // int alpm_decode_signature(const char *base64_data,
+    //                            unsigned char **data, size_t *data_len);
+    let decode: Symbol<fn(*const c_char, *mut *const c_uchar, *mut usize) -> c_int> =
+      try!( self.lib.get(b"alpm_decode_signature\0") );
+
+    let mut data: *const c_uchar = std::ptr::null();
+    let mut data_len: usize = 0;
+
+    if decode(encoded.as_ptr(), &mut data, &mut data_len) != 0 {
+      return Err(Error::new(ErrorKind::Other, "Could not decode base64 signature"));
+    }
+
+    let bytes = std::slice::from_raw_parts(data, data_len).to_vec();
+    // alpm_decode_signature hands back a malloc'd buffer; we've copied it
+    // into `bytes`, so free the original now instead of leaking it.
+    free(data as *mut std::os::raw::c_void);
+
+    Ok(bytes)
+  }
+}
+
+extern "C" {
+  fn free(ptr: *mut std::os::raw::c_void);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{status_from_code, SigStatus};
+
+  #[test]
+  fn maps_known_codes() {
+    assert_eq!(SigStatus::Valid, status_from_code(0));
+    assert_eq!(SigStatus::Invalid, status_from_code(1));
+    assert_eq!(SigStatus::Invalid, status_from_code(2));
+    assert_eq!(SigStatus::KeyUnknown, status_from_code(3));
+    assert_eq!(SigStatus::Invalid, status_from_code(4));
+    assert_eq!(SigStatus::Invalid, status_from_code(5));
+  }
+
+  #[test]
+  fn maps_unknown_code_to_invalid() {
+    assert_eq!(SigStatus::Invalid, status_from_code(99));
+  }
+}
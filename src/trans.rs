@@ -0,0 +1,307 @@
+use std::ffi::{CStr, CString};
+use std::io;
+use std::io::{Error, ErrorKind};
+use std::ops::BitOr;
+use std::os::raw::{c_char, c_int};
+use num::FromPrimitive;
+use so::Symbol;
+
+use db::list_iter;
+use {Alpm, AlpmErrno};
+
+/// Flags controlling a transaction, mirroring `alpm_transflag_t`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransFlags(u32);
+
+impl TransFlags {
+  pub const NONE: TransFlags = TransFlags(0);
+  /// Ignore dependency checks.
+  pub const NODEPS: TransFlags = TransFlags(1 << 0);
+  /// Ignore file conflicts.
+  pub const FORCE: TransFlags = TransFlags(1 << 1);
+  /// Do not save files renamed by a package upgrade (`.pacsave`).
+  pub const NOSAVE: TransFlags = TransFlags(1 << 2);
+  /// Remove also any packages depending on the targets.
+  pub const CASCADE: TransFlags = TransFlags(1 << 4);
+  /// Remove also dependencies of the targets that are no longer required.
+  pub const RECURSE: TransFlags = TransFlags(1 << 5);
+  /// Only modify the database, not the filesystem.
+  pub const DBONLY: TransFlags = TransFlags(1 << 6);
+  /// Do not run install scriptlets.
+  pub const NOSCRIPTLET: TransFlags = TransFlags(1 << 10);
+  /// Install targets as dependencies, not as explicit installs.
+  pub const ALLDEPS: TransFlags = TransFlags(1 << 8);
+  /// Install targets as explicit installs, not as dependencies.
+  pub const ALLEXPLICIT: TransFlags = TransFlags(1 << 14);
+
+  fn bits(self) -> u32 { self.0 }
+}
+
+impl BitOr for TransFlags {
+  type Output = TransFlags;
+  fn bitor(self, rhs: TransFlags) -> TransFlags { TransFlags(self.0 | rhs.0) }
+}
+
+/// Mirrors the head of libalpm's `alpm_depmissing_t`, as returned in the
+/// `alpm_list_t *data` of a failed `alpm_trans_prepare` on
+/// `ALPM_ERR_UNSATISFIED_DEPS`.
+#[repr(C)]
+struct CDepMissing {
+  target: *const c_char,
+  depend: *const usize,
+  causingpkg: *const c_char,
+}
+
+/// A running transaction, started by [`Alpm::trans_init`].
+///
+/// Dropping it releases the transaction (`alpm_trans_release`), whether or
+/// not it was committed, so a transaction that is abandoned after a failed
+/// `trans_prepare` doesn't leave the handle locked.
+pub struct Transaction<'a> {
+  alpm: &'a Alpm,
+}
+
+impl Alpm {
+  /// Start a new transaction with the given flags.
+  pub fn trans_init(&self, flags: TransFlags) -> io::Result<Transaction> {
+    unsafe {
+      // int alpm_trans_init(alpm_handle_t *handle, alpm_transflag_t flags);
+      let trans_init: Symbol<fn(*const usize, u32) -> c_int> = try!( self.lib.get(b"alpm_trans_init\0") );
+
+      if trans_init(self.handle, flags.bits()) != 0 {
+        return Err(self.dep_error("Could not initialize transaction"));
+      }
+
+      Ok(Transaction { alpm: self })
+    }
+  }
+
+  fn dep_error(&self, context: &str) -> Error {
+    unsafe {
+      // alpm_errno_t alpm_errno(alpm_handle_t *handle);
+      match self.lib.get::<fn(*const usize) -> usize>(b"alpm_errno\0") {
+        Ok(errno) => {
+          let code = errno(self.handle);
+          Error::new(ErrorKind::Other, format!("{}: {:?}", context, AlpmErrno::from_usize(code)))
+        }
+        Err(e) => e,
+      }
+    }
+  }
+
+  /// Like [`Alpm::dep_error`], but also consumes the `alpm_list_t *data` that
+  /// `alpm_trans_prepare`/`alpm_trans_commit` hand back on
+  /// `ALPM_ERR_UNSATISFIED_DEPS` or `ALPM_ERR_CONFLICTING_DEPS`, freeing it
+  /// and folding the missing dependencies (or the number of conflicts) it
+  /// names into the error message. `data` may be null.
+  fn trans_dep_error(&self, context: &str, data: *const usize) -> Error {
+    unsafe {
+      let code = match self.lib.get::<fn(*const usize) -> usize>(b"alpm_errno\0") {
+        Ok(errno) => errno(self.handle),
+        Err(e) => return e,
+      };
+
+      let detail = self.describe_depmissing_list(code, data);
+
+      Error::new(ErrorKind::Other, format!("{}: {:?}{}", context, AlpmErrno::from_usize(code), detail))
+    }
+  }
+
+  /// Frees `data` and, for `ALPM_ERR_UNSATISFIED_DEPS`, renders each
+  /// `alpm_depmissing_t` in it as "target requires causingpkg". Conflicts
+  /// (`ALPM_ERR_CONFLICTING_DEPS`) carry `alpm_conflict_t`, whose layout has
+  /// changed across libalpm releases (added hash fields), so we only report
+  /// how many were found rather than risk reading at the wrong offsets.
+  ///
+  /// Reuses `db::list_iter` for the traversal itself rather than re-walking
+  /// `alpm_list_t` by hand; the per-item libalpm free still happens inside
+  /// the closure since each item needs its own free function.
+  unsafe fn describe_depmissing_list(&self, code: usize, data: *const usize) -> String {
+    if data == std::ptr::null() {
+      return String::new();
+    }
+
+    let list_free: Symbol<fn(*const usize)> = match self.lib.get(b"alpm_list_free\0") {
+      Ok(s) => s,
+      Err(_) => return String::new(),
+    };
+
+    let unsatisfied = code == AlpmErrno::ALPM_ERR_UNSATISFIED_DEPS as usize;
+    let free_depmissing: Option<Symbol<fn(*const usize)>> =
+      if unsatisfied { self.lib.get(b"alpm_depmissing_free\0").ok() } else { None };
+    let free_conflict: Option<Symbol<fn(*const usize)>> =
+      if unsatisfied { None } else { self.lib.get(b"alpm_conflict_free\0").ok() };
+
+    let items = match list_iter(&self.lib, data, |item| {
+      let desc = if unsatisfied {
+        let dm = &*(item as *const CDepMissing);
+        Some(format!("{} requires {}", to_string_or_empty(dm.target), to_string_or_empty(dm.causingpkg)))
+      } else {
+        None
+      };
+
+      if let Some(ref free_item) = free_depmissing { free_item(item); }
+      if let Some(ref free_item) = free_conflict { free_item(item); }
+
+      desc
+    }) {
+      Ok(items) => items,
+      Err(_) => return String::new(),
+    };
+
+    list_free(data);
+
+    let count = items.len();
+    let details: Vec<String> = items.into_iter().filter_map(|d| d).collect();
+
+    if unsatisfied && !details.is_empty() {
+      format!(" ({})", details.join(", "))
+    } else if count > 0 {
+      format!(" ({} conflict(s))", count)
+    } else {
+      String::new()
+    }
+  }
+}
+
+unsafe fn to_string_or_empty(s: *const c_char) -> String {
+  if s == std::ptr::null() {
+    String::new()
+  } else {
+    CStr::from_ptr(s).to_string_lossy().into_owned()
+  }
+}
+
+impl<'a> Transaction<'a> {
+  /// Stage a package file for installation.
+  pub fn add_pkg(&self, path: &str) -> io::Result<()> {
+    let filename = try!( CString::new(path) );
+
+    unsafe {
+      // int alpm_pkg_load(alpm_handle_t *handle, const char *filename, int full,
+      //                    alpm_siglevel_t level, alpm_pkg_t **pkg);
+      let pkg_load: Symbol<fn(*const usize, *const c_char, c_int, u32, *mut *const usize) -> c_int> =
+        try!( self.alpm.lib.get(b"alpm_pkg_load\0") );
+      // int alpm_add_pkg(alpm_handle_t *handle, alpm_pkg_t *pkg);
+      let add_pkg: Symbol<fn(*const usize, *const usize) -> c_int> = try!( self.alpm.lib.get(b"alpm_add_pkg\0") );
+
+      let mut pkg: *const usize = std::ptr::null();
+      if pkg_load(self.alpm.handle, filename.as_ptr(), 1, 0, &mut pkg) != 0 {
+        return Err(Error::new(ErrorKind::Other, format!("Could not load package {}", path)));
+      }
+
+      if add_pkg(self.alpm.handle, pkg) != 0 {
+        return Err(self.alpm.dep_error(&format!("Could not stage {} for installation", path)));
+      }
+
+      Ok(())
+    }
+  }
+
+  /// Stage an installed package for removal.
+  pub fn remove_pkg(&self, name: &str) -> io::Result<()> {
+    let cs = try!( CString::new(name) );
+
+    unsafe {
+      // alpm_db_t *alpm_get_localdb(alpm_handle_t *handle);
+      let get_localdb: Symbol<fn(*const usize) -> *const usize> = try!( self.alpm.lib.get(b"alpm_get_localdb\0") );
+      // alpm_list_t *alpm_db_get_pkgcache(alpm_db_t *db);
+      let db_get_pkgcache: Symbol<fn(*const usize) -> *const usize> = try!( self.alpm.lib.get(b"alpm_db_get_pkgcache\0") );
+      // alpm_pkg_t *alpm_pkg_find(alpm_list_t *haystack, const char *needle);
+      let pkg_find_in_list: Symbol<fn(*const usize, *const c_char) -> *const usize> = try!( self.alpm.lib.get(b"alpm_pkg_find\0") );
+      // int alpm_remove_pkg(alpm_handle_t *handle, alpm_pkg_t *pkg);
+      let remove_pkg: Symbol<fn(*const usize, *const usize) -> c_int> = try!( self.alpm.lib.get(b"alpm_remove_pkg\0") );
+
+      let db = get_localdb(self.alpm.handle);
+      let list = db_get_pkgcache(db);
+      let pkg = pkg_find_in_list(list, cs.as_ptr());
+
+      if pkg == std::ptr::null() {
+        return Err(Error::new(ErrorKind::Other, format!("No package {} found!", name)));
+      }
+
+      if remove_pkg(self.alpm.handle, pkg) != 0 {
+        return Err(self.alpm.dep_error(&format!("Could not stage {} for removal", name)));
+      }
+
+      Ok(())
+    }
+  }
+
+  /// Resolve dependencies and check for conflicts among the staged targets.
+  ///
+  /// Fails with the underlying `ALPM_ERR_UNSATISFIED_DEPS` or
+  /// `ALPM_ERR_CONFLICTING_DEPS` error when the transaction can't proceed.
+  pub fn trans_prepare(&self) -> io::Result<()> {
+    unsafe {
+      // int alpm_trans_prepare(alpm_handle_t *handle, alpm_list_t **data);
+      let prepare: Symbol<fn(*const usize, *mut *const usize) -> c_int> = try!( self.alpm.lib.get(b"alpm_trans_prepare\0") );
+
+      let mut data: *const usize = std::ptr::null();
+      if prepare(self.alpm.handle, &mut data) != 0 {
+        return Err(self.alpm.trans_dep_error("Could not prepare transaction", data));
+      }
+
+      Ok(())
+    }
+  }
+
+  /// Commit the prepared transaction, applying it to the system.
+  pub fn trans_commit(&mut self) -> io::Result<()> {
+    unsafe {
+      // int alpm_trans_commit(alpm_handle_t *handle, alpm_list_t **data);
+      let commit: Symbol<fn(*const usize, *mut *const usize) -> c_int> = try!( self.alpm.lib.get(b"alpm_trans_commit\0") );
+
+      let mut data: *const usize = std::ptr::null();
+      if commit(self.alpm.handle, &mut data) != 0 {
+        return Err(self.alpm.trans_dep_error("Could not commit transaction", data));
+      }
+
+      Ok(())
+    }
+  }
+}
+
+impl<'a> Drop for Transaction<'a> {
+  /// Release the transaction so the handle isn't left locked.
+  fn drop(&mut self) {
+    unsafe {
+      // int alpm_trans_release(alpm_handle_t *handle);
+      let release: Symbol<fn(*const usize) -> c_int> = self.alpm.lib.get(b"alpm_trans_release\0").unwrap();
+      release(self.alpm.handle);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::TransFlags;
+
+  #[test]
+  fn bitor_combines_distinct_flags() {
+    let combined = TransFlags::FORCE | TransFlags::NOSAVE;
+    assert_eq!(TransFlags::FORCE.bits() | TransFlags::NOSAVE.bits(), combined.bits());
+  }
+
+  #[test]
+  fn bitor_with_none_is_identity() {
+    assert_eq!(TransFlags::CASCADE, TransFlags::CASCADE | TransFlags::NONE);
+  }
+
+  #[test]
+  fn flags_occupy_distinct_bits() {
+    let all = TransFlags::NODEPS | TransFlags::FORCE | TransFlags::NOSAVE | TransFlags::CASCADE
+      | TransFlags::RECURSE | TransFlags::DBONLY | TransFlags::NOSCRIPTLET | TransFlags::ALLDEPS
+      | TransFlags::ALLEXPLICIT;
+
+    let sum_of_bits: u32 = [
+      TransFlags::NODEPS, TransFlags::FORCE, TransFlags::NOSAVE, TransFlags::CASCADE,
+      TransFlags::RECURSE, TransFlags::DBONLY, TransFlags::NOSCRIPTLET, TransFlags::ALLDEPS,
+      TransFlags::ALLEXPLICIT,
+    ].iter().map(|f| f.bits()).sum();
+
+    // If any two flags shared a bit, OR-ing them all together would have
+    // fewer set bits than summing their individual values.
+    assert_eq!(sum_of_bits, all.bits());
+  }
+}
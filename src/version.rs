@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 //split EVR into release, version and release
 pub fn parse_evr<'a>(s: &'a String) -> Result<(Option<&'a str>, &'a str, Option<&'a str>), ()>{
 
@@ -58,6 +60,185 @@ pub fn parse_evr<'a>(s: &'a String) -> Result<(Option<&'a str>, &'a str, Option<
     }
 }
 
+/// A parsed epoch/version/release triple, comparable with the same rules
+/// pacman's `alpm_pkg_vercmp` uses, without needing a loaded libalpm handle.
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub epoch: u64,
+    pub version: String,
+    pub release: Option<String>,
+}
+
+impl Version {
+    /// Parse a version string such as `2:643.2b-43` or `643.2b`.
+    pub fn parse(s: &str) -> Result<Version, ()> {
+        let owned = s.to_owned();
+        let (epoch, version, release) = try!(parse_evr(&owned));
+
+        let epoch = match epoch {
+            Some(e) => try!(e.parse::<u64>().map_err(|_| ())),
+            None => 0,
+        };
+
+        Ok(Version {
+            epoch: epoch,
+            version: version.to_owned(),
+            release: release.map(|r| r.to_owned()),
+        })
+    }
+
+    fn without_release(&self) -> Version {
+        Version { epoch: self.epoch, version: self.version.clone(), release: None }
+    }
+
+    fn compare(&self, other: &Version) -> Ordering {
+        self.epoch.cmp(&other.epoch)
+            .then_with(|| compare_segments(&self.version, &other.version))
+            .then_with(|| match (&self.release, &other.release) {
+                (&Some(ref a), &Some(ref b)) => compare_segments(a, b),
+                _ => Ordering::Equal,
+            })
+    }
+
+    /// Check whether this version satisfies a dependency spec such as
+    /// `>=1.2-3` or `==1`. When the spec omits a release, releases are
+    /// ignored on both sides, so `1-2` satisfies `==1`.
+    pub fn satisfies(&self, spec: &str) -> bool {
+        let (op, required) = match split_constraint(spec).and_then(|(_, op, v)| Version::parse(v).ok().map(|v| (op, v))) {
+            Some(parts) => parts,
+            None => return false,
+        };
+
+        let lhs = if required.release.is_none() { self.without_release() } else { self.clone() };
+
+        match op {
+            Operator::Lt => lhs.compare(&required) == Ordering::Less,
+            Operator::Le => lhs.compare(&required) != Ordering::Greater,
+            Operator::Eq => lhs.compare(&required) == Ordering::Equal,
+            Operator::Ge => lhs.compare(&required) != Ordering::Less,
+            Operator::Gt => lhs.compare(&required) == Ordering::Greater,
+        }
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool { self.compare(other) == Ordering::Equal }
+}
+impl Eq for Version {}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> { Some(self.compare(other)) }
+}
+impl Ord for Version {
+    fn cmp(&self, other: &Version) -> Ordering { self.compare(other) }
+}
+
+/// The relational operator of a dependency spec, e.g. the `>=` in `libfoo>=1.2-3`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+//split a dependency spec like "libfoo>=1.2-3" into name, operator and version
+fn split_constraint(spec: &str) -> Option<(&str, Operator, &str)> {
+    let bytes = spec.as_bytes();
+
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'<' | b'>' | b'=' => {
+                let two = if i + 1 < bytes.len() { &spec[i..i + 2] } else { "" };
+                let (op, len) = match two {
+                    ">=" => (Operator::Ge, 2),
+                    "<=" => (Operator::Le, 2),
+                    "==" => (Operator::Eq, 2),
+                    _ => match bytes[i] {
+                        b'>' => (Operator::Gt, 1),
+                        b'<' => (Operator::Lt, 1),
+                        _ => (Operator::Eq, 1),
+                    },
+                };
+
+                return Some((&spec[..i], op, &spec[i + len..]));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+//compare two alphanumeric version segments the way rpmvercmp/alpm_pkg_vercmp does:
+//split into runs of digits and runs of letters, numeric runs always outrank
+//alpha runs, numeric runs compare as integers, alpha runs compare lexically
+fn compare_segments(a: &str, b: &str) -> Ordering {
+    if a == b {
+        return Ordering::Equal;
+    }
+
+    let mut a = a;
+    let mut b = b;
+
+    loop {
+        let a_trimmed = a.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let b_trimmed = b.trim_start_matches(|c: char| !c.is_alphanumeric());
+        let sep_cmp = (a.len() - a_trimmed.len()).cmp(&(b.len() - b_trimmed.len()));
+        if !a.is_empty() && !b.is_empty() {
+            a = a_trimmed;
+            b = b_trimmed;
+        }
+
+        if a.is_empty() && b.is_empty() {
+            return Ordering::Equal;
+        } else if a.is_empty() || b.is_empty() {
+            //a leftover alpha run (the "rc1" of "1.0rc1") loses to running out, anything else left over wins
+            let b_newer = a.starts_with(char::is_alphabetic) || (a.is_empty() && !b.starts_with(char::is_alphabetic));
+            return if b_newer { Ordering::Less } else { Ordering::Greater };
+        } else if sep_cmp != Ordering::Equal {
+            //a longer run of separators wins
+            return sep_cmp;
+        }
+
+        let a_digit = a.chars().next().unwrap().is_ascii_digit();
+        let b_digit = b.chars().next().unwrap().is_ascii_digit();
+
+        if a_digit != b_digit {
+            return if a_digit { Ordering::Greater } else { Ordering::Less };
+        }
+
+        let (a_seg, a_rest) = take_segment(a, a_digit);
+        let (b_seg, b_rest) = take_segment(b, b_digit);
+
+        let cmp = if a_digit {
+            let a_num = a_seg.trim_start_matches('0');
+            let b_num = b_seg.trim_start_matches('0');
+            a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num))
+        } else {
+            a_seg.cmp(b_seg)
+        };
+
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+
+        a = a_rest;
+        b = b_rest;
+    }
+}
+
+//take the leading run of same-type (digit or alpha) characters
+fn take_segment(s: &str, digit: bool) -> (&str, &str) {
+    let end = s.char_indices()
+        .find(|&(_, c)| !c.is_alphanumeric() || c.is_ascii_digit() != digit)
+        .map(|(i, _)| i)
+        .unwrap_or(s.len());
+
+    s.split_at(end)
+}
+
 #[cfg(test)]
 mod tests{
     #[test]
@@ -79,4 +260,26 @@ mod tests{
         assert!(tup.2.is_none());
         assert_eq!(format!("{}", tup.1), s);
     }
+
+    #[test]
+    fn version_ordering(){
+        use super::Version;
+
+        assert!(Version::parse("1").unwrap() < Version::parse("1.0-2").unwrap());
+        assert!(Version::parse("1.1").unwrap() < Version::parse("1.2").unwrap());
+        assert!(Version::parse("1.9").unwrap() < Version::parse("2").unwrap());
+        assert_eq!(Version::parse("1.0").unwrap(), Version::parse("1.0-2").unwrap());
+        assert_eq!(Version::parse("1:1-1").unwrap(), Version::parse("1:1-1").unwrap());
+        assert!(Version::parse("2.0-1").unwrap() > Version::parse("1.0-1").unwrap());
+    }
+
+    #[test]
+    fn satisfies_ignores_release_when_spec_has_none(){
+        use super::Version;
+
+        assert!(Version::parse("1-2").unwrap().satisfies("==1"));
+        assert!(Version::parse("1.2-3").unwrap().satisfies(">=1.2-3"));
+        assert!(!Version::parse("1.1-1").unwrap().satisfies(">=1.2-3"));
+        assert!(Version::parse("1.5").unwrap().satisfies("<2"));
+    }
 }